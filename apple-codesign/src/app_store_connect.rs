@@ -5,18 +5,51 @@
 use {
     crate::AppleCodesignError,
     jsonwebtoken::{Algorithm, EncodingKey, Header},
+    hmac::{Hmac, Mac},
     log::error,
-    reqwest::blocking::Client,
+    reqwest::blocking::{Client, RequestBuilder},
     serde::{Deserialize, Serialize},
     serde_json::Value,
-    std::{path::Path, sync::Mutex, time::SystemTime},
+    sha2::{Digest, Sha256},
+    std::{
+        io::Read,
+        path::Path,
+        sync::Mutex,
+        time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+    },
 };
 
+type HmacSha256 = Hmac<Sha256>;
+
+/// AWS region assumed for the Notary service's S3 bucket.
+///
+/// The Notary API does not report the bucket's region in
+/// [NewSubmissionResponseDataAttributes], and Apple has only ever issued
+/// `us-west-2` buckets for notarization uploads. If that ever changes, this
+/// constant (and the endpoint derived from it) is the single place to update.
+const NOTARY_S3_REGION: &str = "us-west-2";
+
+/// Size of each part in a multipart upload, and the threshold above which the
+/// multipart path is used instead of a single `PutObject`.
+const MULTIPART_CHUNK_SIZE: usize = 100 * 1024 * 1024;
+
+/// Maximum JWT token lifetime accepted by App Store Connect, in seconds.
+const MAX_TOKEN_DURATION: u64 = 1200;
+
+/// Default lifetime of minted JWT tokens, in seconds.
+const DEFAULT_TOKEN_DURATION: u64 = 300;
+
+/// How close to expiry a cached token may be before it is re-minted, in seconds.
+const TOKEN_EXPIRATION_SKEW: u64 = 30;
+
 pub const ITUNES_PRODUCER_SERVICE_URL: &str = "https://contentdelivery.itunes.apple.com/WebObjects/MZLabelService.woa/json/MZITunesProducerService";
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 struct ConnectTokenRequest {
-    iss: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    iss: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sub: Option<String>,
     iat: u64,
     exp: u64,
     aud: String,
@@ -44,7 +77,7 @@ pub type AppStoreConnectToken = String;
 #[derive(Clone)]
 pub struct ConnectTokenEncoder {
     key_id: String,
-    issuer_id: String,
+    issuer_id: Option<String>,
     encoding_key: EncodingKey,
 }
 
@@ -52,9 +85,12 @@ impl ConnectTokenEncoder {
     /// Construct an instance from an [EncodingKey] instance.
     ///
     /// This is the lowest level API and ultimately what all constructors use.
+    ///
+    /// `issuer_id` is [None] for "individual" API keys, which have no issuer and
+    /// instead authenticate with `iss` omitted and a `sub` claim of `"user"`.
     pub fn from_jwt_encoding_key(
         key_id: String,
-        issuer_id: String,
+        issuer_id: Option<String>,
         encoding_key: EncodingKey,
     ) -> Self {
         Self {
@@ -67,7 +103,7 @@ impl ConnectTokenEncoder {
     /// Construct an instance from a DER encoded ECDSA private key.
     pub fn from_ecdsa_der(
         key_id: String,
-        issuer_id: String,
+        issuer_id: Option<String>,
         der_data: &[u8],
     ) -> Result<Self, AppleCodesignError> {
         let encoding_key = EncodingKey::from_ec_der(der_data);
@@ -78,7 +114,7 @@ impl ConnectTokenEncoder {
     /// Create a token from a PEM encoded ECDSA private key.
     pub fn from_ecdsa_pem(
         key_id: String,
-        issuer_id: String,
+        issuer_id: Option<String>,
         pem_data: &[u8],
     ) -> Result<Self, AppleCodesignError> {
         let encoding_key = EncodingKey::from_ec_pem(pem_data)?;
@@ -89,7 +125,7 @@ impl ConnectTokenEncoder {
     /// Create a token from a PEM encoded ECDSA private key in a filesystem path.
     pub fn from_ecdsa_pem_path(
         key_id: String,
-        issuer_id: String,
+        issuer_id: Option<String>,
         path: impl AsRef<Path>,
     ) -> Result<Self, AppleCodesignError> {
         let data = std::fs::read(path.as_ref())?;
@@ -101,7 +137,10 @@ impl ConnectTokenEncoder {
     ///
     /// e.g. `DEADBEEF42`. This looks for an `AuthKey_<id>.p8` file in default search
     /// locations like `~/.appstoreconnect/private_keys`.
-    pub fn from_api_key_id(key_id: String, issuer_id: String) -> Result<Self, AppleCodesignError> {
+    pub fn from_api_key_id(
+        key_id: String,
+        issuer_id: Option<String>,
+    ) -> Result<Self, AppleCodesignError> {
         let mut search_paths = vec![std::env::current_dir()?.join("private_keys")];
 
         if let Some(home) = dirs::home_dir() {
@@ -131,6 +170,9 @@ impl ConnectTokenEncoder {
     /// Using the private key and key metadata bound to this instance, we issue a new JWT
     /// for the requested duration.
     pub fn new_token(&self, duration: u64) -> Result<AppStoreConnectToken, AppleCodesignError> {
+        // App Store Connect rejects tokens whose lifetime exceeds 20 minutes.
+        let duration = duration.min(MAX_TOKEN_DURATION);
+
         let header = Header {
             kid: Some(self.key_id.clone()),
             alg: Algorithm::ES256,
@@ -142,8 +184,19 @@ impl ConnectTokenEncoder {
             .expect("calculating UNIX time should never fail")
             .as_secs();
 
+        // Team-based keys authenticate with the issuer ID in `iss`. Individual
+        // keys omit `iss` and instead carry the literal `sub` claim "user"; the
+        // key ID travels in the `kid` header, not `sub`. See Apple's "Generating
+        // Tokens for API Requests" docs, "Create the JWT Payload":
+        // https://developer.apple.com/documentation/appstoreconnectapi/generating-tokens-for-api-requests
+        let (iss, sub) = match &self.issuer_id {
+            Some(issuer_id) => (Some(issuer_id.clone()), None),
+            None => (None, Some("user".to_string())),
+        };
+
         let claims = ConnectTokenRequest {
-            iss: self.issuer_id.clone(),
+            iss,
+            sub,
             iat: now,
             exp: now + duration,
             aud: "appstoreconnect-v1".to_string(),
@@ -249,6 +302,14 @@ impl SubmissionResponse {
     /// Convert the instance into a [Result].
     ///
     /// Will yield [Err] if the notarization/upload was not successful.
+    ///
+    /// For a rejected submission this can only produce a generic
+    /// [NotarizeRejected](AppleCodesignError::NotarizeRejected), since the
+    /// per-file issues live in the developer log which has to be fetched
+    /// separately. Callers that want those diagnostics should go through
+    /// [AppStoreConnectClient::wait_for_submission] (or
+    /// [AppStoreConnectClient::submission_result]), which fetches the log and
+    /// builds an error carrying the real issue count and summary.
     pub fn into_result(self) -> Result<Self, AppleCodesignError> {
         match self.data.attributes.status {
             SubmissionResponseStatus::Accepted => Ok(self),
@@ -287,33 +348,163 @@ pub struct SubmissionLogResponse {
     pub meta: Value,
 }
 
+/// A minted JWT token together with the instant at which it expires.
+struct CachedToken {
+    token: AppStoreConnectToken,
+    expires: SystemTime,
+}
+
+/// Credentials used to authenticate requests to the Notary API.
+///
+/// Apple's `notarytool` accepts either an App Store Connect API key (used to
+/// mint JWTs) or an Apple ID paired with an app-specific password and team ID.
+///
+/// Only [ConnectToken](Self::ConnectToken) can authenticate the `notary/v2` REST
+/// endpoints used by [AppStoreConnectClient]; the Apple-ID form is a different
+/// transport in `notarytool` and is rejected by [AppStoreConnectClient::new].
+pub enum AppStoreConnectAuthentication {
+    /// Authenticate by minting JWTs from an App Store Connect API key.
+    ConnectToken(ConnectTokenEncoder),
+
+    /// Authenticate with an Apple ID, app-specific password, and team ID.
+    ///
+    /// Not accepted by the `notary/v2` REST API — see the type-level docs.
+    AppleId {
+        apple_id: String,
+        app_specific_password: String,
+        team_id: String,
+    },
+}
+
+impl From<ConnectTokenEncoder> for AppStoreConnectAuthentication {
+    fn from(encoder: ConnectTokenEncoder) -> Self {
+        Self::ConnectToken(encoder)
+    }
+}
+
+/// A single issue reported in a notarization developer log.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotarizationLogIssue {
+    pub severity: String,
+    pub message: String,
+    #[serde(default)]
+    pub path: Option<String>,
+    #[serde(default)]
+    pub doc_url: Option<String>,
+    #[serde(default)]
+    pub architecture: Option<String>,
+}
+
+/// A parsed notarization developer log document.
+///
+/// This is the JSON returned from the `developerLogUrl` that
+/// [get_submission_log](AppStoreConnectClient::get_submission_log) points at. It
+/// carries the same diagnostics `notarytool log` prints.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotarizationLog {
+    pub status: String,
+    pub status_summary: String,
+    #[serde(default)]
+    pub issues: Option<Vec<NotarizationLogIssue>>,
+}
+
 /// A client for App Store Connect API.
 ///
 /// The client isn't generic. Don't get any ideas.
 pub struct AppStoreConnectClient {
     client: Client,
-    connect_token: ConnectTokenEncoder,
-    token: Mutex<Option<AppStoreConnectToken>>,
+    authentication: AppStoreConnectAuthentication,
+    token: Mutex<Option<CachedToken>>,
 }
 
 impl AppStoreConnectClient {
-    pub fn new(connect_token: ConnectTokenEncoder) -> Result<Self, AppleCodesignError> {
+    pub fn new(
+        authentication: impl Into<AppStoreConnectAuthentication>,
+    ) -> Result<Self, AppleCodesignError> {
+        let authentication = authentication.into();
+
+        // The `notary/v2` REST endpoints this client talks to only accept API-key
+        // JWT bearer auth. notarytool's Apple-ID mode is a separate transport, so
+        // reject those credentials up front rather than handing back a client that
+        // builds an archive and then fails on its first request.
+        if matches!(authentication, AppStoreConnectAuthentication::AppleId { .. }) {
+            return Err(AppleCodesignError::NotarizeAppleIdNotSupported);
+        }
+
         Ok(Self {
             client: crate::ticket_lookup::default_client()?,
-            connect_token,
+            authentication,
             token: Mutex::new(None),
         })
     }
 
-    fn get_token(&self) -> Result<String, AppleCodesignError> {
+    fn get_token(&self, encoder: &ConnectTokenEncoder) -> Result<String, AppleCodesignError> {
         let mut token = self.token.lock().unwrap();
 
-        // TODO need to handle token expiration.
-        if token.is_none() {
-            token.replace(self.connect_token.new_token(300)?);
+        // Re-mint whenever the cached token is missing or close enough to its
+        // expiry that a request made with it could be rejected with a 401.
+        let needs_refresh = match token.as_ref() {
+            Some(cached) => {
+                cached.expires <= SystemTime::now() + Duration::from_secs(TOKEN_EXPIRATION_SKEW)
+            }
+            None => true,
+        };
+
+        if needs_refresh {
+            let value = encoder.new_token(DEFAULT_TOKEN_DURATION)?;
+            token.replace(CachedToken {
+                token: value,
+                expires: SystemTime::now() + Duration::from_secs(DEFAULT_TOKEN_DURATION),
+            });
         }
 
-        Ok(token.as_ref().unwrap().clone())
+        Ok(token.as_ref().unwrap().token.clone())
+    }
+
+    /// Apply the configured authentication to an outgoing request.
+    fn authenticate(&self, builder: RequestBuilder) -> Result<RequestBuilder, AppleCodesignError> {
+        match &self.authentication {
+            AppStoreConnectAuthentication::ConnectToken(encoder) => {
+                Ok(builder.bearer_auth(self.get_token(encoder)?))
+            }
+            // Unreachable: [new](Self::new) rejects Apple-ID credentials before a
+            // client is ever constructed. Kept for match exhaustiveness.
+            AppStoreConnectAuthentication::AppleId { .. } => {
+                Err(AppleCodesignError::NotarizeAppleIdNotSupported)
+            }
+        }
+    }
+
+    /// Validate that a submission notification is well-formed before sending it.
+    ///
+    /// The Notary API only supports the `webhook` channel, whose target must be an
+    /// `https` URL it can POST the completion callback to.
+    fn validate_notification(
+        notification: &NewSubmissionRequestNotification,
+    ) -> Result<(), AppleCodesignError> {
+        if notification.channel != "webhook" {
+            return Err(AppleCodesignError::NotarizeInvalidNotification(format!(
+                "unsupported notification channel: {}",
+                notification.channel
+            )));
+        }
+
+        let url = reqwest::Url::parse(&notification.target).map_err(|e| {
+            AppleCodesignError::NotarizeInvalidNotification(format!(
+                "invalid webhook target URL: {}",
+                e
+            ))
+        })?;
+
+        if url.scheme() != "https" {
+            return Err(AppleCodesignError::NotarizeInvalidNotification(
+                "webhook target must be an https URL".to_string(),
+            ));
+        }
+
+        Ok(())
     }
 
     /// Create a submission to the Notary API.
@@ -322,20 +513,37 @@ impl AppStoreConnectClient {
         sha256: &str,
         submission_name: &str,
     ) -> Result<NewSubmissionResponse, AppleCodesignError> {
-        let token = self.get_token()?;
+        self.create_submission_with_notifications(sha256, submission_name, Vec::new())
+    }
+
+    /// Create a submission to the Notary API, registering completion notifications.
+    ///
+    /// Each notification asks the service to POST to a callback when notarization
+    /// finishes — e.g. `channel: "webhook"` with a `target` URL — so CI systems can
+    /// be notified asynchronously instead of polling. The channel/target pair of
+    /// every notification is validated before the request is sent.
+    pub fn create_submission_with_notifications(
+        &self,
+        sha256: &str,
+        submission_name: &str,
+        notifications: Vec<NewSubmissionRequestNotification>,
+    ) -> Result<NewSubmissionResponse, AppleCodesignError> {
+        for notification in &notifications {
+            Self::validate_notification(notification)?;
+        }
 
         let body = NewSubmissionRequest {
-            notifications: Vec::new(),
+            notifications,
             sha256: sha256.to_string(),
             submission_name: submission_name.to_string(),
         };
-        let req = self
-            .client
-            .post(APPLE_NOTARY_SUBMIT_SOFTWARE_URL)
-            .bearer_auth(token)
-            .header("Accept", "application/json")
-            .header("Content-Type", "application/json")
-            .json(&body);
+        let req = self.authenticate(
+            self.client
+                .post(APPLE_NOTARY_SUBMIT_SOFTWARE_URL)
+                .header("Accept", "application/json")
+                .header("Content-Type", "application/json")
+                .json(&body),
+        )?;
 
         let response = req.send()?;
 
@@ -351,21 +559,45 @@ impl AppStoreConnectClient {
         }
     }
 
+    /// Upload a software archive to the Notary service's S3 bucket.
+    ///
+    /// [create_submission](Self::create_submission) hands back temporary AWS
+    /// session credentials along with the bucket and object key the archive must
+    /// be uploaded to. This performs that upload over the existing [reqwest]
+    /// client, signing each request with AWS Signature Version 4 (including the
+    /// STS session token), so callers don't have to pull in an AWS SDK.
+    ///
+    /// Archives larger than [MULTIPART_CHUNK_SIZE] are streamed with the S3
+    /// multipart API so the whole file is never held in memory at once; smaller
+    /// archives use a single `PutObject`.
+    pub fn upload_submission(
+        &self,
+        attributes: &NewSubmissionResponseDataAttributes,
+        data_path: &Path,
+    ) -> Result<(), AppleCodesignError> {
+        let upload = S3Upload::new(&self.client, attributes);
+        let len = std::fs::metadata(data_path)?.len();
+
+        if len as usize <= MULTIPART_CHUNK_SIZE {
+            upload.put_object(data_path)
+        } else {
+            upload.put_object_multipart(data_path)
+        }
+    }
+
     /// Fetch the status of a Notary API submission.
     pub fn get_submission(
         &self,
         submission_id: &str,
     ) -> Result<SubmissionResponse, AppleCodesignError> {
-        let token = self.get_token()?;
-
-        let req = self
-            .client
-            .get(format!(
-                "{}/{}",
-                APPLE_NOTARY_SUBMIT_SOFTWARE_URL, submission_id
-            ))
-            .bearer_auth(token)
-            .header("Accept", "application/json");
+        let req = self.authenticate(
+            self.client
+                .get(format!(
+                    "{}/{}",
+                    APPLE_NOTARY_SUBMIT_SOFTWARE_URL, submission_id
+                ))
+                .header("Accept", "application/json"),
+        )?;
 
         let response = req.send()?;
 
@@ -374,18 +606,85 @@ impl AppStoreConnectClient {
         Ok(res_data)
     }
 
+    /// Poll a submission until it reaches a terminal status or the timeout elapses.
+    ///
+    /// [get_submission](Self::get_submission) reports whatever status the service
+    /// currently holds, so callers waiting on notarization have to write their own
+    /// polling loop. This repeatedly polls, treating
+    /// [InProgress](SubmissionResponseStatus::InProgress) as "keep waiting", and
+    /// passes the first response whose status is terminal through
+    /// [submission_result](Self::submission_result): an accepted submission is
+    /// returned as [Ok], any other terminal status as an error (a rejected or
+    /// invalid one carrying the issues from its developer log). Polls use exponential
+    /// backoff starting at a few seconds and capped at ~30 seconds. If no terminal
+    /// status is observed before `timeout` elapses, [NotarizeTimeout] is returned.
+    ///
+    /// [NotarizeTimeout]: AppleCodesignError::NotarizeTimeout
+    pub fn wait_for_submission(
+        &self,
+        submission_id: &str,
+        timeout: Duration,
+    ) -> Result<SubmissionResponse, AppleCodesignError> {
+        let start = Instant::now();
+        let mut backoff = Duration::from_secs(3);
+        let max_backoff = Duration::from_secs(30);
+
+        loop {
+            let response = self.get_submission(submission_id)?;
+
+            if response.data.attributes.status != SubmissionResponseStatus::InProgress {
+                return self.submission_result(response);
+            }
+
+            let remaining = timeout.saturating_sub(start.elapsed());
+
+            if remaining.is_zero() {
+                return Err(AppleCodesignError::NotarizeTimeout);
+            }
+
+            // Clamp the sleep to the remaining time so we never overshoot the
+            // caller's deadline by up to a full backoff interval.
+            std::thread::sleep(std::cmp::min(backoff, remaining));
+            backoff = std::cmp::min(backoff * 2, max_backoff);
+        }
+    }
+
+    /// Convert a terminal submission response into a [Result], enriching failures.
+    ///
+    /// Unlike [SubmissionResponse::into_result], a rejected or invalid submission
+    /// fetches the developer log and returns a
+    /// [NotarizeRejected](AppleCodesignError::NotarizeRejected) carrying the real
+    /// issue count and a human-readable summary instead of a generic error.
+    ///
+    /// If the log fetch itself fails (e.g. the log isn't ready yet, or a network
+    /// error), the submission still surfaces as rejected/invalid via the generic
+    /// error from [SubmissionResponse::into_result] rather than leaking a
+    /// confusing transport error.
+    pub fn submission_result(
+        &self,
+        response: SubmissionResponse,
+    ) -> Result<SubmissionResponse, AppleCodesignError> {
+        match response.data.attributes.status {
+            SubmissionResponseStatus::Rejected | SubmissionResponseStatus::Invalid => {
+                match self.notarization_rejection(&response.data.id) {
+                    Ok(err) => Err(err),
+                    Err(_) => response.into_result(),
+                }
+            }
+            _ => response.into_result(),
+        }
+    }
+
     /// Fetch details about a single completed notarization.
     pub fn get_submission_log(&self, submission_id: &str) -> Result<Value, AppleCodesignError> {
-        let token = self.get_token()?;
-
-        let req = self
-            .client
-            .get(format!(
-                "{}/{}/logs",
-                APPLE_NOTARY_SUBMIT_SOFTWARE_URL, submission_id
-            ))
-            .bearer_auth(token)
-            .header("Accept", "application/json");
+        let req = self.authenticate(
+            self.client
+                .get(format!(
+                    "{}/{}/logs",
+                    APPLE_NOTARY_SUBMIT_SOFTWARE_URL, submission_id
+                ))
+                .header("Accept", "application/json"),
+        )?;
 
         let response = req.send()?;
 
@@ -397,4 +696,462 @@ impl AppStoreConnectClient {
 
         Ok(logs)
     }
+
+    /// Fetch the developer log for a submission and parse it into typed form.
+    pub fn get_submission_log_document(
+        &self,
+        submission_id: &str,
+    ) -> Result<NotarizationLog, AppleCodesignError> {
+        let value = self.get_submission_log(submission_id)?;
+
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// Fetch the log for a failed submission and build a [NotarizeRejected] error.
+    ///
+    /// The returned error carries the number of issues Apple reported and a
+    /// human-readable summary of the per-file errors, giving callers the same
+    /// diagnostics as `notarytool log` instead of a generic failure.
+    ///
+    /// [NotarizeRejected]: AppleCodesignError::NotarizeRejected
+    pub fn notarization_rejection(
+        &self,
+        submission_id: &str,
+    ) -> Result<AppleCodesignError, AppleCodesignError> {
+        let log = self.get_submission_log_document(submission_id)?;
+        let issues = log.issues.unwrap_or_default();
+
+        let summary = if issues.is_empty() {
+            log.status_summary
+        } else {
+            issues
+                .iter()
+                .map(|issue| {
+                    let mut line = format!("{}: {}", issue.severity, issue.message);
+
+                    if let Some(path) = &issue.path {
+                        line.push_str(&format!(" ({})", path));
+                    }
+
+                    if let Some(architecture) = &issue.architecture {
+                        line.push_str(&format!(" [{}]", architecture));
+                    }
+
+                    line
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+
+        Ok(AppleCodesignError::NotarizeRejected(issues.len(), summary))
+    }
+}
+
+/// Uploads a software archive to an S3 bucket using temporary session
+/// credentials, signing requests with AWS Signature Version 4.
+///
+/// This reuses the crate's [reqwest] client rather than pulling in an AWS SDK,
+/// keeping the module synchronous. It implements just enough of the S3 REST API
+/// to `PutObject` small archives and to drive a streaming multipart upload for
+/// large ones.
+struct S3Upload<'a> {
+    client: &'a Client,
+    access_key: &'a str,
+    secret_key: &'a str,
+    session_token: &'a str,
+    bucket: &'a str,
+    object: &'a str,
+}
+
+impl<'a> S3Upload<'a> {
+    fn new(client: &'a Client, attributes: &'a NewSubmissionResponseDataAttributes) -> Self {
+        Self {
+            client,
+            access_key: &attributes.aws_access_key_id,
+            secret_key: &attributes.aws_secret_access_key,
+            session_token: &attributes.aws_session_token,
+            bucket: &attributes.bucket,
+            object: &attributes.object,
+        }
+    }
+
+    /// Virtual-hosted-style endpoint for the bucket.
+    fn host(&self) -> String {
+        format!("{}.s3.{}.amazonaws.com", self.bucket, NOTARY_S3_REGION)
+    }
+
+    /// Build a SigV4-signed request for the given method, query, and body.
+    fn signed(
+        &self,
+        method: reqwest::Method,
+        query: &[(&str, &str)],
+        body: Vec<u8>,
+    ) -> RequestBuilder {
+        let host = self.host();
+        let canonical_uri = format!("/{}", uri_encode(self.object, false));
+
+        let mut pairs: Vec<(String, String)> = query
+            .iter()
+            .map(|(k, v)| (uri_encode(k, true), uri_encode(v, true)))
+            .collect();
+        pairs.sort();
+        let canonical_query = pairs
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        let payload_hash = sha256_hex(&body);
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system clock is before the UNIX epoch")
+            .as_secs();
+        let (amz_date, date_stamp) = amz_datetime(now);
+
+        let canonical_headers = format!(
+            "host:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\nx-amz-security-token:{}\n",
+            host, payload_hash, amz_date, self.session_token
+        );
+        let signed_headers = "host;x-amz-content-sha256;x-amz-date;x-amz-security-token";
+
+        let canonical_request = format!(
+            "{}\n{}\n{}\n{}\n{}\n{}",
+            method.as_str(),
+            canonical_uri,
+            canonical_query,
+            canonical_headers,
+            signed_headers,
+            payload_hash,
+        );
+
+        let scope = format!("{}/{}/s3/aws4_request", date_stamp, NOTARY_S3_REGION);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            sha256_hex(canonical_request.as_bytes()),
+        );
+
+        let signing_key = signing_key(self.secret_key, &date_stamp, NOTARY_S3_REGION, "s3");
+        let signature = hex_encode(&hmac_sha256(&signing_key, string_to_sign.as_bytes()));
+
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            self.access_key, scope, signed_headers, signature,
+        );
+
+        let url = if canonical_query.is_empty() {
+            format!("https://{}{}", host, canonical_uri)
+        } else {
+            format!("https://{}{}?{}", host, canonical_uri, canonical_query)
+        };
+
+        self.client
+            .request(method, url)
+            .header("Host", host)
+            .header("x-amz-content-sha256", payload_hash)
+            .header("x-amz-date", amz_date)
+            .header("x-amz-security-token", self.session_token)
+            .header("Authorization", authorization)
+            .body(body)
+    }
+
+    /// Upload the whole archive in a single `PutObject`.
+    fn put_object(&self, data_path: &Path) -> Result<(), AppleCodesignError> {
+        let data = std::fs::read(data_path)?;
+        let response = self.signed(reqwest::Method::PUT, &[], data).send()?;
+
+        Self::check(response, "PutObject")
+    }
+
+    /// Upload the archive via the S3 multipart API, streaming it in chunks.
+    fn put_object_multipart(&self, data_path: &Path) -> Result<(), AppleCodesignError> {
+        let upload_id = self.create_multipart_upload()?;
+
+        match self.upload_parts(data_path, &upload_id) {
+            Ok(parts) => self.complete_multipart_upload(&upload_id, &parts),
+            Err(e) => {
+                // Best-effort cleanup so a failure doesn't leave a dangling upload.
+                let _ = self.abort_multipart_upload(&upload_id);
+                Err(e)
+            }
+        }
+    }
+
+    fn create_multipart_upload(&self) -> Result<String, AppleCodesignError> {
+        let response = self
+            .signed(reqwest::Method::POST, &[("uploads", "")], Vec::new())
+            .send()?;
+
+        if !response.status().is_success() {
+            error!("S3 CreateMultipartUpload failed: {}", response.status());
+            return Err(AppleCodesignError::NotarizeUpload);
+        }
+
+        let body = response.text()?;
+
+        extract_xml_tag(&body, "UploadId").ok_or(AppleCodesignError::NotarizeUpload)
+    }
+
+    fn upload_parts(
+        &self,
+        data_path: &Path,
+        upload_id: &str,
+    ) -> Result<Vec<(usize, String)>, AppleCodesignError> {
+        let mut file = std::fs::File::open(data_path)?;
+        let mut parts = Vec::new();
+        let mut part_number = 1usize;
+
+        loop {
+            let mut buf = vec![0u8; MULTIPART_CHUNK_SIZE];
+            let mut filled = 0;
+
+            while filled < buf.len() {
+                let read = file.read(&mut buf[filled..])?;
+                if read == 0 {
+                    break;
+                }
+                filled += read;
+            }
+
+            if filled == 0 {
+                break;
+            }
+
+            buf.truncate(filled);
+
+            let part_str = part_number.to_string();
+            let response = self
+                .signed(
+                    reqwest::Method::PUT,
+                    &[("partNumber", &part_str), ("uploadId", upload_id)],
+                    buf,
+                )
+                .send()?;
+
+            if !response.status().is_success() {
+                error!("S3 UploadPart {} failed: {}", part_number, response.status());
+                return Err(AppleCodesignError::NotarizeUpload);
+            }
+
+            let etag = response
+                .headers()
+                .get("ETag")
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.to_string())
+                .ok_or(AppleCodesignError::NotarizeUpload)?;
+
+            parts.push((part_number, etag));
+
+            if filled < MULTIPART_CHUNK_SIZE {
+                break;
+            }
+
+            part_number += 1;
+        }
+
+        Ok(parts)
+    }
+
+    fn complete_multipart_upload(
+        &self,
+        upload_id: &str,
+        parts: &[(usize, String)],
+    ) -> Result<(), AppleCodesignError> {
+        let mut body = String::from("<CompleteMultipartUpload>");
+
+        for (number, etag) in parts {
+            body.push_str(&format!(
+                "<Part><PartNumber>{}</PartNumber><ETag>{}</ETag></Part>",
+                number, etag
+            ));
+        }
+
+        body.push_str("</CompleteMultipartUpload>");
+
+        let response = self
+            .signed(
+                reqwest::Method::POST,
+                &[("uploadId", upload_id)],
+                body.into_bytes(),
+            )
+            .send()?;
+
+        Self::check(response, "CompleteMultipartUpload")
+    }
+
+    fn abort_multipart_upload(&self, upload_id: &str) -> Result<(), AppleCodesignError> {
+        let response = self
+            .signed(
+                reqwest::Method::DELETE,
+                &[("uploadId", upload_id)],
+                Vec::new(),
+            )
+            .send()?;
+
+        Self::check(response, "AbortMultipartUpload")
+    }
+
+    fn check(
+        response: reqwest::blocking::Response,
+        context: &str,
+    ) -> Result<(), AppleCodesignError> {
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            error!("S3 {} failed: {}", context, response.status());
+            if let Ok(body) = response.text() {
+                error!("{}", body);
+            }
+            Err(AppleCodesignError::NotarizeUpload)
+        }
+    }
+}
+
+/// Percent-encode a string per the AWS SigV4 canonicalization rules.
+///
+/// All bytes outside the RFC 3986 unreserved set are encoded; `/` is preserved
+/// when `encode_slash` is false so object keys keep their path structure.
+fn uri_encode(input: &str, encode_slash: bool) -> String {
+    let mut out = String::with_capacity(input.len());
+
+    for byte in input.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                out.push(byte as char)
+            }
+            b'/' if !encode_slash => out.push('/'),
+            _ => out.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+
+    out
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len() * 2);
+
+    for byte in bytes {
+        out.push_str(&format!("{:02x}", byte));
+    }
+
+    out
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    hex_encode(&Sha256::digest(data))
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Derive the AWS SigV4 signing key from the secret access key and request scope.
+fn signing_key(secret: &str, date_stamp: &str, region: &str, service: &str) -> Vec<u8> {
+    let k_date = hmac_sha256(format!("AWS4{}", secret).as_bytes(), date_stamp.as_bytes());
+    let k_region = hmac_sha256(&k_date, region.as_bytes());
+    let k_service = hmac_sha256(&k_region, service.as_bytes());
+    hmac_sha256(&k_service, b"aws4_request")
+}
+
+/// Extract the text content of the first `<tag>...</tag>` element in `xml`.
+///
+/// The S3 responses this parses are tiny and well-formed, so a full XML parser
+/// would be overkill; this pulls out the single value we need.
+fn extract_xml_tag(xml: &str, tag: &str) -> Option<String> {
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+    let start = xml.find(&open)? + open.len();
+    let end = xml[start..].find(&close)? + start;
+
+    Some(xml[start..end].to_string())
+}
+
+/// Format a UNIX timestamp as the `(YYYYMMDDTHHMMSSZ, YYYYMMDD)` pair SigV4
+/// requires, avoiding a dependency on a datetime crate.
+fn amz_datetime(secs: u64) -> (String, String) {
+    let days = (secs / 86_400) as i64;
+    let rem = secs % 86_400;
+    let (hour, minute, second) = (rem / 3600, (rem % 3600) / 60, rem % 60);
+
+    // Civil-from-days, after Howard Hinnant's `civil_from_days` algorithm.
+    let z = days + 719_468;
+    let era = if z >= 0 { z } else { z - 146_096 } / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let year = yoe + era * 400;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = if month <= 2 { year + 1 } else { year };
+
+    (
+        format!(
+            "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+            year, month, day, hour, minute, second
+        ),
+        format!("{:04}{:02}{:02}", year, month, day),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sha256_of_empty_payload() {
+        // The hash S3 expects for an empty request body.
+        assert_eq!(
+            sha256_hex(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+
+    #[test]
+    fn signing_key_matches_aws_example() {
+        // Known-answer vector from AWS's "deriving a signing key" documentation.
+        let key = signing_key(
+            "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+            "20120215",
+            "us-east-1",
+            "iam",
+        );
+
+        assert_eq!(
+            hex_encode(&key),
+            "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d"
+        );
+    }
+
+    #[test]
+    fn amz_datetime_epoch() {
+        assert_eq!(
+            amz_datetime(0),
+            ("19700101T000000Z".to_string(), "19700101".to_string())
+        );
+    }
+
+    #[test]
+    fn amz_datetime_known_instant() {
+        // 2015-08-30T12:36:00Z, the date from the AWS SigV4 test suite.
+        assert_eq!(
+            amz_datetime(1_440_938_160),
+            ("20150830T123600Z".to_string(), "20150830".to_string())
+        );
+    }
+
+    #[test]
+    fn uri_encode_preserves_path_slashes() {
+        assert_eq!(uri_encode("prod/My Key~1.zip", false), "prod/My%20Key~1.zip");
+    }
+
+    #[test]
+    fn uri_encode_escapes_slashes_and_reserved_chars() {
+        assert_eq!(uri_encode("a/b:c", true), "a%2Fb%3Ac");
+        assert_eq!(uri_encode("-_.~", true), "-_.~");
+    }
 }